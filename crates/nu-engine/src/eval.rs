@@ -7,7 +7,7 @@ use nu_protocol::{
     },
     engine::{EngineState, Stack},
     Config, HistoryFileFormat, IntoInterruptiblePipelineData, IntoPipelineData, PipelineData,
-    Range, ShellError, Span, Spanned, Unit, Value, VarId, ENV_VARIABLE_ID,
+    Range, ShellError, Span, Spanned, SyntaxShape, Unit, Value, VarId, ENV_VARIABLE_ID,
 };
 use nu_utils::stdout_write_all_and_flush;
 use std::collections::HashMap;
@@ -25,6 +25,223 @@ pub fn eval_operator(op: &Expression) -> Result<Operator, ShellError> {
     }
 }
 
+/// Generalizes `in`/`not-in` membership testing across container-like values.
+trait Contains {
+    fn contains(&self, op: Span, haystack: &Value, span: Span) -> Result<Value, ShellError>;
+}
+
+impl Contains for Value {
+    fn contains(&self, op: Span, haystack: &Value, span: Span) -> Result<Value, ShellError> {
+        match haystack {
+            Value::List { vals, .. } => Ok(Value::boolean(
+                vals.iter()
+                    .any(|item| self.eq(op, item, span).map(|v| v.is_true()).unwrap_or(false)),
+                span,
+            )),
+            Value::Record { cols, .. } => {
+                let needle = self.as_string()?;
+                Ok(Value::boolean(cols.iter().any(|col| col == &needle), span))
+            }
+            Value::String { val, .. } => {
+                let needle = self.as_string()?;
+                Ok(Value::boolean(val.contains(&needle), span))
+            }
+            Value::Binary { val: haystack, .. } => match self {
+                Value::Binary { val: needle, .. } => Ok(Value::boolean(
+                    needle.is_empty() || haystack.windows(needle.len()).any(|w| w == &needle[..]),
+                    span,
+                )),
+                _ => Err(ShellError::TypeMismatch("binary".into(), span)),
+            },
+            // Ranges and cell paths already go through the pre-existing `r#in`, and
+            // anything else not covered above falls back to it too, so this only widens
+            // (never removes) what `in`/`not-in` could already do.
+            _ => self.r#in(op, haystack, span),
+        }
+    }
+}
+
+/// Folds literal arithmetic/boolean subexpressions and `const` bindings in a block.
+pub fn optimize_block(engine_state: &EngineState, block: &Block) -> Block {
+    let mut output = block.clone();
+    let mut consts: HashMap<VarId, Expression> = HashMap::new();
+
+    for pipeline in output.pipelines.iter_mut() {
+        for element in pipeline.elements.iter_mut() {
+            let expr = match element {
+                PipelineElement::Expression(_, expr)
+                | PipelineElement::Redirection(_, _, expr)
+                | PipelineElement::And(_, expr)
+                | PipelineElement::Or(_, expr) => expr,
+            };
+
+            *expr = optimize_expression(expr, &mut consts);
+            record_const_binding(engine_state, expr, &mut consts);
+        }
+    }
+
+    output
+}
+
+/// Records a `const` binding's folded value so later `Expr::Var` references to it fold too.
+fn record_const_binding(
+    engine_state: &EngineState,
+    expr: &Expression,
+    consts: &mut HashMap<VarId, Expression>,
+) {
+    if let Expr::Call(call) = &expr.expr {
+        if engine_state.get_decl(call.decl_id).name() == "const" {
+            if let (Some(Argument::Positional(name)), Some(Argument::Positional(value))) =
+                (call.arguments.get(0), call.arguments.get(1))
+            {
+                // The value arrives as the `= <expr>` keyword argument.
+                let value = match &value.expr {
+                    Expr::Keyword(_, _, inner) => inner.as_ref(),
+                    _ => value,
+                };
+                if let Expr::VarDecl(var_id) = &name.expr {
+                    if is_literal(&value.expr) {
+                        consts.insert(*var_id, value.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_literal(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Int(_) | Expr::Float(_) | Expr::Bool(_) | Expr::String(_) | Expr::Binary(_)
+    )
+}
+
+/// Folds literal `BinaryOp`/`UnaryNot` nodes, substitutes recorded `const` values, and
+/// recurses into call arguments (e.g. a `const` binding's value expression).
+fn optimize_expression(expr: &Expression, consts: &mut HashMap<VarId, Expression>) -> Expression {
+    match &expr.expr {
+        Expr::Var(var_id) => consts.get(var_id).cloned().unwrap_or_else(|| expr.clone()),
+        Expr::UnaryNot(inner) => {
+            let inner = optimize_expression(inner, consts);
+            let mut folded = expr.clone();
+            if let Expr::Bool(val) = inner.expr {
+                folded.expr = Expr::Bool(!val);
+            } else {
+                folded.expr = Expr::UnaryNot(Box::new(inner));
+            }
+            folded
+        }
+        Expr::BinaryOp(lhs, op, rhs) => {
+            let lhs = optimize_expression(lhs, consts);
+            let rhs = optimize_expression(rhs, consts);
+
+            if is_literal(&lhs.expr) && is_literal(&rhs.expr) {
+                if let Expr::Operator(operator) = &op.expr {
+                    if let Some(folded) = fold_literal_op(operator, &lhs, &rhs, op.span, expr.span)
+                    {
+                        return folded;
+                    }
+                }
+            }
+
+            let mut unfolded = expr.clone();
+            unfolded.expr = Expr::BinaryOp(Box::new(lhs), op.clone(), Box::new(rhs));
+            unfolded
+        }
+        Expr::Call(call) => {
+            let mut call = call.clone();
+            for arg in call.arguments.iter_mut() {
+                match arg {
+                    Argument::Positional(arg_expr) => *arg_expr = optimize_expression(arg_expr, consts),
+                    Argument::Named((_, _, Some(arg_expr))) => {
+                        *arg_expr = optimize_expression(arg_expr, consts)
+                    }
+                    _ => {}
+                }
+            }
+            let mut folded = expr.clone();
+            folded.expr = Expr::Call(call);
+            folded
+        }
+        Expr::Keyword(keyword, keyword_span, inner) => {
+            let mut folded = expr.clone();
+            folded.expr = Expr::Keyword(
+                keyword.clone(),
+                *keyword_span,
+                Box::new(optimize_expression(inner, consts)),
+            );
+            folded
+        }
+        _ => expr.clone(),
+    }
+}
+
+fn fold_literal_op(
+    operator: &Operator,
+    lhs: &Expression,
+    rhs: &Expression,
+    op_span: Span,
+    span: Span,
+) -> Option<Expression> {
+    let lhs_val = literal_to_value(lhs)?;
+    let rhs_val = literal_to_value(rhs)?;
+
+    let folded = match operator {
+        Operator::Math(Math::Plus) => lhs_val.add(op_span, &rhs_val, span),
+        Operator::Math(Math::Minus) => lhs_val.sub(op_span, &rhs_val, span),
+        Operator::Math(Math::Multiply) => lhs_val.mul(op_span, &rhs_val, span),
+        Operator::Math(Math::Divide) => lhs_val.div(op_span, &rhs_val, span),
+        Operator::Math(Math::Modulo) => lhs_val.modulo(op_span, &rhs_val, span),
+        Operator::Math(Math::FloorDivision) => lhs_val.floor_div(op_span, &rhs_val, span),
+        Operator::Math(Math::Pow) => lhs_val.pow(op_span, &rhs_val, span),
+        Operator::Boolean(Boolean::And) => lhs_val.and(op_span, &rhs_val, span),
+        Operator::Boolean(Boolean::Or) => lhs_val.or(op_span, &rhs_val, span),
+        Operator::Boolean(Boolean::Xor) => lhs_val.xor(op_span, &rhs_val, span),
+        Operator::Comparison(Comparison::LessThan) => lhs_val.lt(op_span, &rhs_val, span),
+        Operator::Comparison(Comparison::LessThanOrEqual) => lhs_val.lte(op_span, &rhs_val, span),
+        Operator::Comparison(Comparison::GreaterThan) => lhs_val.gt(op_span, &rhs_val, span),
+        Operator::Comparison(Comparison::GreaterThanOrEqual) => {
+            lhs_val.gte(op_span, &rhs_val, span)
+        }
+        Operator::Comparison(Comparison::Equal) => lhs_val.eq(op_span, &rhs_val, span),
+        Operator::Comparison(Comparison::NotEqual) => lhs_val.ne(op_span, &rhs_val, span),
+        _ => return None,
+    };
+
+    let mut folded_expr = lhs.clone();
+    folded_expr.span = span;
+    folded_expr.expr = value_to_expr(folded.ok()?)?;
+    Some(folded_expr)
+}
+
+fn literal_to_value(expr: &Expression) -> Option<Value> {
+    match &expr.expr {
+        Expr::Int(i) => Some(Value::int(*i, expr.span)),
+        Expr::Float(f) => Some(Value::float(*f, expr.span)),
+        Expr::Bool(b) => Some(Value::boolean(*b, expr.span)),
+        Expr::String(s) => Some(Value::String {
+            val: s.clone(),
+            span: expr.span,
+        }),
+        Expr::Binary(b) => Some(Value::Binary {
+            val: b.clone(),
+            span: expr.span,
+        }),
+        _ => None,
+    }
+}
+
+fn value_to_expr(value: Value) -> Option<Expr> {
+    match value {
+        Value::Int { val, .. } => Some(Expr::Int(val)),
+        Value::Float { val, .. } => Some(Expr::Float(val)),
+        Value::Bool { val, .. } => Some(Expr::Bool(val)),
+        Value::String { val, .. } => Some(Expr::String(val)),
+        Value::Binary { val, .. } => Some(Expr::Binary(val)),
+        _ => None,
+    }
+}
+
 pub fn eval_call(
     engine_state: &EngineState,
     caller_stack: &mut Stack,
@@ -71,9 +288,11 @@ pub fn eval_call(
 
             if let Some(arg) = call.positional_nth(param_idx) {
                 let result = eval_expression(engine_state, caller_stack, arg)?;
+                let result = check_arg_type(result, &param.shape, arg.span)?;
                 callee_stack.add_var(var_id, result);
             } else if let Some(arg) = &param.default_value {
                 let result = eval_expression(engine_state, caller_stack, arg)?;
+                let result = check_arg_type(result, &param.shape, arg.span)?;
                 callee_stack.add_var(var_id, result);
             } else {
                 callee_stack.add_var(var_id, Value::nothing(call.head));
@@ -88,6 +307,7 @@ pub fn eval_call(
                     + decl.signature().optional_positional.len(),
             ) {
                 let result = eval_expression(engine_state, caller_stack, arg)?;
+                let result = check_arg_type(result, &rest_positional.shape, arg.span)?;
                 rest_items.push(result);
             }
 
@@ -115,10 +335,18 @@ pub fn eval_call(
                     if call_named.0.item == named.long {
                         if let Some(arg) = &call_named.2 {
                             let result = eval_expression(engine_state, caller_stack, arg)?;
+                            let result = match &named.arg {
+                                Some(shape) => check_arg_type(result, shape, arg.span)?,
+                                None => result,
+                            };
 
                             callee_stack.add_var(var_id, result);
                         } else if let Some(arg) = &named.default_value {
                             let result = eval_expression(engine_state, caller_stack, arg)?;
+                            let result = match &named.arg {
+                                Some(shape) => check_arg_type(result, shape, arg.span)?,
+                                None => result,
+                            };
 
                             callee_stack.add_var(var_id, result);
                         } else {
@@ -133,6 +361,10 @@ pub fn eval_call(
                         callee_stack.add_var(var_id, Value::boolean(false, call.head))
                     } else if let Some(arg) = &named.default_value {
                         let result = eval_expression(engine_state, caller_stack, arg)?;
+                        let result = match &named.arg {
+                            Some(shape) => check_arg_type(result, shape, arg.span)?,
+                            None => result,
+                        };
 
                         callee_stack.add_var(var_id, result);
                     } else {
@@ -164,6 +396,27 @@ pub fn eval_call(
     }
 }
 
+/// Checks a value against a parameter's declared `SyntaxShape`, coercing int to float/number.
+fn check_arg_type(value: Value, shape: &SyntaxShape, span: Span) -> Result<Value, ShellError> {
+    match (shape, &value) {
+        (SyntaxShape::Int, Value::Int { .. })
+        | (SyntaxShape::Number, Value::Int { .. } | Value::Float { .. })
+        | (SyntaxShape::Float, Value::Float { .. })
+        | (SyntaxShape::String, Value::String { .. })
+        | (SyntaxShape::Boolean, Value::Bool { .. }) => Ok(value),
+        (SyntaxShape::Float, Value::Int { val, .. }) => Ok(Value::float(*val as f64, span)),
+        (
+            SyntaxShape::Int
+            | SyntaxShape::Number
+            | SyntaxShape::Float
+            | SyntaxShape::String
+            | SyntaxShape::Boolean,
+            _,
+        ) => Err(ShellError::TypeMismatch(shape.to_string(), span)),
+        _ => Ok(value),
+    }
+}
+
 /// Redirect the environment from callee to the caller.
 pub fn redirect_env(engine_state: &EngineState, caller_stack: &mut Stack, callee_stack: &Stack) {
     // Grab all environment variables from the callee
@@ -183,6 +436,103 @@ pub fn redirect_env(engine_state: &EngineState, caller_stack: &mut Stack, callee
     }
 }
 
+/// Depth-first visitor over a `Block`/`Expression` tree; `f` returning `false` prunes/stops early.
+/// Mirrors `Contains` in adding a capability to a foreign-crate type via a local trait.
+pub trait Walk {
+    fn walk(&self, engine_state: &EngineState, f: &mut impl FnMut(&Expression) -> bool) -> bool;
+}
+
+impl Walk for Block {
+    fn walk(&self, engine_state: &EngineState, f: &mut impl FnMut(&Expression) -> bool) -> bool {
+        for pipeline in &self.pipelines {
+            for element in &pipeline.elements {
+                if !walk_pipeline_element(engine_state, element, f) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+fn walk_pipeline_element(
+    engine_state: &EngineState,
+    element: &PipelineElement,
+    f: &mut impl FnMut(&Expression) -> bool,
+) -> bool {
+    match element {
+        PipelineElement::Expression(_, expr)
+        | PipelineElement::Redirection(_, _, expr)
+        | PipelineElement::And(_, expr)
+        | PipelineElement::Or(_, expr) => expr.walk(engine_state, f),
+    }
+}
+
+impl Walk for Expression {
+    fn walk(&self, engine_state: &EngineState, f: &mut impl FnMut(&Expression) -> bool) -> bool {
+        if !f(self) {
+            return false;
+        }
+
+        match &self.expr {
+            Expr::BinaryOp(lhs, op, rhs) => {
+                lhs.walk(engine_state, f) && op.walk(engine_state, f) && rhs.walk(engine_state, f)
+            }
+            Expr::UnaryNot(inner) => inner.walk(engine_state, f),
+            Expr::ValueWithUnit(inner, _) => inner.walk(engine_state, f),
+            Expr::Range(from, next, to, _) => {
+                from.as_ref().map_or(true, |e| e.walk(engine_state, f))
+                    && next.as_ref().map_or(true, |e| e.walk(engine_state, f))
+                    && to.as_ref().map_or(true, |e| e.walk(engine_state, f))
+            }
+            Expr::Call(call) => walk_call_args(engine_state, call, f),
+            Expr::ExternalCall(head, args, _) => {
+                head.walk(engine_state, f) && args.iter().all(|arg| arg.walk(engine_state, f))
+            }
+            Expr::FullCellPath(cell_path) => cell_path.head.walk(engine_state, f),
+            Expr::List(items) => items.iter().all(|item| item.walk(engine_state, f)),
+            Expr::Table(headers, rows) => {
+                headers.iter().all(|h| h.walk(engine_state, f))
+                    && rows
+                        .iter()
+                        .all(|row| row.iter().all(|cell| cell.walk(engine_state, f)))
+            }
+            Expr::Record(fields) => fields
+                .iter()
+                .all(|(col, val)| col.walk(engine_state, f) && val.walk(engine_state, f)),
+            Expr::Keyword(_, _, inner) => inner.walk(engine_state, f),
+            Expr::StringInterpolation(parts) => {
+                parts.iter().all(|part| part.walk(engine_state, f))
+            }
+            Expr::Subexpression(block_id)
+            | Expr::RowCondition(block_id)
+            | Expr::Closure(block_id)
+            | Expr::Block(block_id) => engine_state.get_block(*block_id).walk(engine_state, f),
+            _ => true,
+        }
+    }
+}
+
+fn walk_call_args(
+    engine_state: &EngineState,
+    call: &Call,
+    f: &mut impl FnMut(&Expression) -> bool,
+) -> bool {
+    for arg in call.positional_iter() {
+        if !arg.walk(engine_state, f) {
+            return false;
+        }
+    }
+    for named in call.named_iter() {
+        if let Some(arg) = &named.2 {
+            if !arg.walk(engine_state, f) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
 /// Eval external expression
 ///
 /// It returns PipelineData with a boolean flag, indicate that if the external runs to failed.
@@ -262,6 +612,9 @@ pub fn eval_expression(
         }),
         Expr::ValueWithUnit(e, unit) => match eval_expression(engine_state, stack, e)? {
             Value::Int { val, .. } => Ok(compute(val, unit.item, unit.span)),
+            // A compound literal like `1hr30min` can't be reduced to a single `(magnitude,
+            // unit)` pair at parse time, so it's carried through as the raw token string.
+            Value::String { val, .. } => parse_compound_duration(&val, expr.span),
             x => Err(ShellError::CantConvert(
                 "unit value".into(),
                 x.get_type().to_string(),
@@ -400,8 +753,12 @@ pub fn eval_expression(
                         Comparison::GreaterThanOrEqual => lhs.gte(op_span, &rhs, expr.span),
                         Comparison::Equal => lhs.eq(op_span, &rhs, expr.span),
                         Comparison::NotEqual => lhs.ne(op_span, &rhs, expr.span),
-                        Comparison::In => lhs.r#in(op_span, &rhs, expr.span),
-                        Comparison::NotIn => lhs.not_in(op_span, &rhs, expr.span),
+                        // `in`/`not-in` both go through `Contains::contains` below.
+                        Comparison::In => lhs.contains(op_span, &rhs, expr.span),
+                        Comparison::NotIn => match lhs.contains(op_span, &rhs, expr.span)? {
+                            Value::Bool { val, .. } => Ok(Value::boolean(!val, expr.span)),
+                            other => Ok(other),
+                        },
                         Comparison::RegexMatch => {
                             lhs.regex_match(engine_state, op_span, &rhs, false, expr.span)
                         }
@@ -1224,127 +1581,299 @@ pub fn eval_variable(
     }
 }
 
+/// Multiplies `size` by `multiplier`, erroring instead of wrapping on overflow.
+fn checked_filesize(size: i64, multiplier: i64, span: Span) -> Value {
+    match size.checked_mul(multiplier) {
+        Some(val) => Value::Filesize { val, span },
+        None => Value::Error {
+            error: ShellError::GenericError(
+                "filesize too large".into(),
+                "filesize too large".into(),
+                Some(span),
+                None,
+                Vec::new(),
+            ),
+        },
+    }
+}
+
+fn checked_duration(size: i64, multiplier: i64, span: Span) -> Value {
+    match size.checked_mul(multiplier) {
+        Some(val) => Value::Duration { val, span },
+        None => duration_too_large(span),
+    }
+}
+
+fn duration_too_large(span: Span) -> Value {
+    Value::Error {
+        error: ShellError::GenericError(
+            "duration too large".into(),
+            "duration too large".into(),
+            Some(span),
+            None,
+            Vec::new(),
+        ),
+    }
+}
+
+/// Maps a compound-literal unit suffix (`"hr"`, `"min"`, ...) to its `Unit`.
+fn unit_from_suffix(suffix: &str) -> Option<Unit> {
+    match suffix {
+        "ns" => Some(Unit::Nanosecond),
+        "us" | "µs" => Some(Unit::Microsecond),
+        "ms" => Some(Unit::Millisecond),
+        "sec" => Some(Unit::Second),
+        "min" => Some(Unit::Minute),
+        "hr" => Some(Unit::Hour),
+        "day" => Some(Unit::Day),
+        "wk" => Some(Unit::Week),
+        _ => None,
+    }
+}
+
+fn invalid_duration_literal(span: Span) -> ShellError {
+    ShellError::GenericError(
+        "invalid duration literal".into(),
+        "invalid duration literal".into(),
+        Some(span),
+        None,
+        Vec::new(),
+    )
+}
+
+/// Parses a compound duration literal (`1hr30min`, `2day6hr15min`, `1.5hr`) into a single
+/// `Value::Duration`. Units must appear in strictly decreasing magnitude; only the first
+/// segment may be fractional.
+pub fn parse_compound_duration(token: &str, span: Span) -> Result<Value, ShellError> {
+    let mut rest = token;
+    let mut total_nanos: i64 = 0;
+    let mut previous_factor = i64::MAX;
+    let mut first = true;
+
+    while !rest.is_empty() {
+        let digits_end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        if digits_end == 0 {
+            return Err(invalid_duration_literal(span));
+        }
+        let (number, after_number) = rest.split_at(digits_end);
+
+        let unit_end = after_number
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(after_number.len());
+        let (unit_str, remainder) = after_number.split_at(unit_end);
+        if unit_str.is_empty() {
+            return Err(invalid_duration_literal(span));
+        }
+
+        let unit = unit_from_suffix(unit_str).ok_or_else(|| invalid_duration_literal(span))?;
+        let factor = nanos_per_unit(unit).ok_or_else(|| invalid_duration_literal(span))?;
+
+        if factor >= previous_factor {
+            return Err(invalid_duration_literal(span));
+        }
+        previous_factor = factor;
+
+        let is_fractional = number.contains('.');
+        if is_fractional && !first {
+            return Err(invalid_duration_literal(span));
+        }
+
+        let segment_nanos = if is_fractional {
+            let magnitude: f64 = number
+                .parse()
+                .map_err(|_| invalid_duration_literal(span))?;
+            (magnitude * factor as f64) as i64
+        } else {
+            let magnitude: i64 = number
+                .parse()
+                .map_err(|_| invalid_duration_literal(span))?;
+            match magnitude.checked_mul(factor) {
+                Some(nanos) => nanos,
+                None => return Ok(duration_too_large(span)),
+            }
+        };
+
+        total_nanos = match total_nanos.checked_add(segment_nanos) {
+            Some(sum) => sum,
+            None => return Ok(duration_too_large(span)),
+        };
+
+        first = false;
+        rest = remainder;
+    }
+
+    if first {
+        return Err(invalid_duration_literal(span));
+    }
+
+    Ok(Value::Duration {
+        val: total_nanos,
+        span,
+    })
+}
+
 fn compute(size: i64, unit: Unit, span: Span) -> Value {
     match unit {
         Unit::Byte => Value::Filesize { val: size, span },
-        Unit::Kilobyte => Value::Filesize {
-            val: size * 1000,
-            span,
-        },
-        Unit::Megabyte => Value::Filesize {
-            val: size * 1000 * 1000,
-            span,
-        },
-        Unit::Gigabyte => Value::Filesize {
-            val: size * 1000 * 1000 * 1000,
-            span,
-        },
-        Unit::Terabyte => Value::Filesize {
-            val: size * 1000 * 1000 * 1000 * 1000,
-            span,
-        },
-        Unit::Petabyte => Value::Filesize {
-            val: size * 1000 * 1000 * 1000 * 1000 * 1000,
-            span,
-        },
-        Unit::Exabyte => Value::Filesize {
-            val: size * 1000 * 1000 * 1000 * 1000 * 1000 * 1000,
-            span,
-        },
-        Unit::Zettabyte => Value::Filesize {
-            val: size * 1000 * 1000 * 1000 * 1000 * 1000 * 1000 * 1000,
-            span,
-        },
+        Unit::Kilobyte => checked_filesize(size, 1000, span),
+        Unit::Megabyte => checked_filesize(size, 1000 * 1000, span),
+        Unit::Gigabyte => checked_filesize(size, 1000 * 1000 * 1000, span),
+        Unit::Terabyte => checked_filesize(size, 1000 * 1000 * 1000 * 1000, span),
+        Unit::Petabyte => checked_filesize(size, 1000 * 1000 * 1000 * 1000 * 1000, span),
+        Unit::Exabyte => checked_filesize(size, 1000 * 1000 * 1000 * 1000 * 1000 * 1000, span),
+        // 1000^7 does not fit in an i64, so multiply in two checked steps.
+        Unit::Zettabyte => {
+            match checked_filesize(size, 1000 * 1000 * 1000 * 1000 * 1000 * 1000, span) {
+                Value::Filesize { val, .. } => checked_filesize(val, 1000, span),
+                error => error,
+            }
+        }
 
-        Unit::Kibibyte => Value::Filesize {
-            val: size * 1024,
-            span,
-        },
-        Unit::Mebibyte => Value::Filesize {
-            val: size * 1024 * 1024,
-            span,
-        },
-        Unit::Gibibyte => Value::Filesize {
-            val: size * 1024 * 1024 * 1024,
-            span,
-        },
-        Unit::Tebibyte => Value::Filesize {
-            val: size * 1024 * 1024 * 1024 * 1024,
-            span,
-        },
-        Unit::Pebibyte => Value::Filesize {
-            val: size * 1024 * 1024 * 1024 * 1024 * 1024,
-            span,
-        },
-        Unit::Exbibyte => Value::Filesize {
-            val: size * 1024 * 1024 * 1024 * 1024 * 1024 * 1024,
-            span,
-        },
-        Unit::Zebibyte => Value::Filesize {
-            val: size * 1024 * 1024 * 1024 * 1024 * 1024 * 1024 * 1024,
-            span,
-        },
+        Unit::Kibibyte => checked_filesize(size, 1024, span),
+        Unit::Mebibyte => checked_filesize(size, 1024 * 1024, span),
+        Unit::Gibibyte => checked_filesize(size, 1024 * 1024 * 1024, span),
+        Unit::Tebibyte => checked_filesize(size, 1024 * 1024 * 1024 * 1024, span),
+        Unit::Pebibyte => checked_filesize(size, 1024 * 1024 * 1024 * 1024 * 1024, span),
+        Unit::Exbibyte => checked_filesize(size, 1024 * 1024 * 1024 * 1024 * 1024 * 1024, span),
+        // 1024^7 does not fit in an i64, so multiply in two checked steps.
+        Unit::Zebibyte => {
+            match checked_filesize(size, 1024 * 1024 * 1024 * 1024 * 1024 * 1024, span) {
+                Value::Filesize { val, .. } => checked_filesize(val, 1024, span),
+                error => error,
+            }
+        }
 
         Unit::Nanosecond => Value::Duration { val: size, span },
-        Unit::Microsecond => Value::Duration {
-            val: size * 1000,
-            span,
-        },
-        Unit::Millisecond => Value::Duration {
-            val: size * 1000 * 1000,
-            span,
-        },
-        Unit::Second => Value::Duration {
-            val: size * 1000 * 1000 * 1000,
-            span,
-        },
-        Unit::Minute => match size.checked_mul(1000 * 1000 * 1000 * 60) {
-            Some(val) => Value::Duration { val, span },
-            None => Value::Error {
-                error: ShellError::GenericError(
-                    "duration too large".into(),
-                    "duration too large".into(),
-                    Some(span),
-                    None,
-                    Vec::new(),
-                ),
-            },
-        },
-        Unit::Hour => match size.checked_mul(1000 * 1000 * 1000 * 60 * 60) {
-            Some(val) => Value::Duration { val, span },
-            None => Value::Error {
-                error: ShellError::GenericError(
-                    "duration too large".into(),
-                    "duration too large".into(),
-                    Some(span),
-                    None,
-                    Vec::new(),
-                ),
-            },
-        },
-        Unit::Day => match size.checked_mul(1000 * 1000 * 1000 * 60 * 60 * 24) {
-            Some(val) => Value::Duration { val, span },
-            None => Value::Error {
-                error: ShellError::GenericError(
-                    "duration too large".into(),
-                    "duration too large".into(),
-                    Some(span),
-                    None,
-                    Vec::new(),
-                ),
-            },
-        },
-        Unit::Week => match size.checked_mul(1000 * 1000 * 1000 * 60 * 60 * 24 * 7) {
-            Some(val) => Value::Duration { val, span },
-            None => Value::Error {
-                error: ShellError::GenericError(
-                    "duration too large".into(),
-                    "duration too large".into(),
-                    Some(span),
-                    None,
-                    Vec::new(),
-                ),
-            },
-        },
+        Unit::Microsecond => checked_duration(size, 1000, span),
+        Unit::Millisecond => checked_duration(size, 1000 * 1000, span),
+        Unit::Second => checked_duration(size, 1000 * 1000 * 1000, span),
+        Unit::Minute => checked_duration(size, 1000 * 1000 * 1000 * 60, span),
+        Unit::Hour => checked_duration(size, 1000 * 1000 * 1000 * 60 * 60, span),
+        Unit::Day => checked_duration(size, 1000 * 1000 * 1000 * 60 * 60 * 24, span),
+        Unit::Week => checked_duration(size, 1000 * 1000 * 1000 * 60 * 60 * 24 * 7, span),
+    }
+}
+
+/// The nanosecond factor for each duration unit. `None` for units with no nanosecond
+/// factor (the filesize units).
+pub fn nanos_per_unit(unit: Unit) -> Option<i64> {
+    match unit {
+        Unit::Nanosecond => Some(1),
+        Unit::Microsecond => Some(1000),
+        Unit::Millisecond => Some(1000 * 1000),
+        Unit::Second => Some(1000 * 1000 * 1000),
+        Unit::Minute => Some(1000 * 1000 * 1000 * 60),
+        Unit::Hour => Some(1000 * 1000 * 1000 * 60 * 60),
+        Unit::Day => Some(1000 * 1000 * 1000 * 60 * 60 * 24),
+        Unit::Week => Some(1000 * 1000 * 1000 * 60 * 60 * 24 * 7),
+        _ => None,
+    }
+}
+
+/// Formats nanoseconds as a fixed unit with fixed decimal places, e.g. `1_250_000_000` ns
+/// at `Unit::Second`/precision 3 -> `"1.250"`. `None` for units `nanos_per_unit` doesn't cover.
+pub fn format_duration_fixed(nanos: i64, unit: Unit, precision: u32) -> Option<String> {
+    let factor = nanos_per_unit(unit)?;
+    let scale = 10i64.checked_pow(precision)?;
+
+    let scaled = (nanos as i128) * (scale as i128) / (factor as i128);
+    // Take the sign from `scaled` itself, not from `whole` below -- when the magnitude is
+    // smaller than one whole unit (e.g. -0.5sec), `whole` truncates to 0 and loses it.
+    let sign = if scaled < 0 { "-" } else { "" };
+    let scaled = scaled.unsigned_abs();
+    let whole = scaled / scale as u128;
+    let frac = scaled % scale as u128;
+
+    Some(format!(
+        "{sign}{whole}.{frac:0width$}",
+        width = precision as usize
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_arg_type_coerces_int_to_float() {
+        let span = Span::new(0, 0);
+        let result = check_arg_type(Value::int(3, span), &SyntaxShape::Float, span).unwrap();
+        assert!(matches!(result, Value::Float { val, .. } if val == 3.0));
+    }
+
+    #[test]
+    fn check_arg_type_rejects_mismatched_shape() {
+        let span = Span::new(0, 0);
+        let err = check_arg_type(Value::boolean(true, span), &SyntaxShape::Int, span).unwrap_err();
+        assert!(matches!(err, ShellError::TypeMismatch(_, _)));
+    }
+
+    #[test]
+    fn check_arg_type_passes_through_unchecked_shapes() {
+        let span = Span::new(0, 0);
+        let result = check_arg_type(Value::boolean(true, span), &SyntaxShape::Any, span).unwrap();
+        assert!(matches!(result, Value::Bool { val: true, .. }));
+    }
+
+    #[test]
+    fn parse_compound_duration_sums_two_segments() {
+        let span = Span::new(0, 0);
+        let value = parse_compound_duration("1hr30min", span).unwrap();
+        let expected = 90 * 60 * 1_000_000_000i64;
+        assert!(matches!(value, Value::Duration { val, .. } if val == expected));
+    }
+
+    #[test]
+    fn parse_compound_duration_sums_three_segments() {
+        let span = Span::new(0, 0);
+        let value = parse_compound_duration("2day6hr15min", span).unwrap();
+        let expected = 2 * 24 * 60 * 60 * 1_000_000_000i64
+            + 6 * 60 * 60 * 1_000_000_000i64
+            + 15 * 60 * 1_000_000_000i64;
+        assert!(matches!(value, Value::Duration { val, .. } if val == expected));
+    }
+
+    #[test]
+    fn parse_compound_duration_allows_leading_fraction() {
+        let span = Span::new(0, 0);
+        let value = parse_compound_duration("1.5hr", span).unwrap();
+        let expected = 90 * 60 * 1_000_000_000i64;
+        assert!(matches!(value, Value::Duration { val, .. } if val == expected));
+    }
+
+    #[test]
+    fn parse_compound_duration_rejects_increasing_magnitude() {
+        let span = Span::new(0, 0);
+        assert!(parse_compound_duration("30min1hr", span).is_err());
+    }
+
+    #[test]
+    fn parse_compound_duration_overflow_yields_duration_too_large() {
+        let span = Span::new(0, 0);
+        let value = parse_compound_duration("99999999999999wk", span).unwrap();
+        assert!(matches!(value, Value::Error { .. }));
+    }
+
+    #[test]
+    fn format_duration_fixed_formats_whole_and_fraction() {
+        assert_eq!(
+            format_duration_fixed(1_250_000_000, Unit::Second, 3),
+            Some("1.250".into())
+        );
+    }
+
+    #[test]
+    fn format_duration_fixed_preserves_sign_below_one_unit() {
+        assert_eq!(
+            format_duration_fixed(-500_000_000, Unit::Second, 3),
+            Some("-0.500".into())
+        );
+    }
+
+    #[test]
+    fn format_duration_fixed_none_for_unit_with_no_fixed_factor() {
+        assert_eq!(format_duration_fixed(1, Unit::Byte, 3), None);
     }
 }